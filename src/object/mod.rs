@@ -1,19 +1,27 @@
 use {
-    alloc::{boxed::Box, sync::Arc, vec::Vec},
+    alloc::{
+        boxed::Box,
+        collections::VecDeque,
+        sync::{Arc, Weak},
+        vec::Vec,
+    },
     core::{
         fmt::Debug,
         future::Future,
         pin::Pin,
         sync::atomic::*,
-        task::{Context, Poll},
+        task::{Context, Poll, Waker},
+        time::Duration,
     },
     downcast_rs::{impl_downcast, DowncastSync},
+    futures::Stream,
     spin::Mutex,
 };
 
-pub use {super::*, handle::*, rights::*, signal::*};
+pub use {super::*, handle::*, port::*, rights::*, signal::*};
 
 mod handle;
+mod port;
 mod rights;
 mod signal;
 
@@ -21,7 +29,10 @@ pub trait KernelObject: DowncastSync + Debug {
     fn id(&self) -> KoID;
     fn type_name(&self) -> &'static str;
     fn signal(&self) -> Signal;
-    fn add_signal_callback(&self, callback: SignalHandler);
+    fn add_signal_callback(&self, interest: Signal, callback: SignalHandler) -> RegistrationToken;
+    fn signal_change(&self, clear: Signal, set: Signal);
+    /// The `KoID` of this object's paired peer (e.g. the other end of a channel), or 0 if none.
+    fn related_koid(&self) -> KoID;
 }
 
 impl_downcast!(sync KernelObject);
@@ -29,14 +40,69 @@ impl_downcast!(sync KernelObject);
 /// The base struct of a kernel object.
 pub struct KObjectBase {
     pub id: KoID,
-    inner: Mutex<KObjectBaseInner>,
+    inner: Arc<Mutex<KObjectBaseInner>>,
+}
+
+/// One registered signal callback, keyed by its slot in `KObjectBaseInner::registrations`.
+struct Registration {
+    interest: Signal,
+    callback: SignalHandler,
 }
 
 /// The mutable part of `KObjectBase`.
 #[derive(Default)]
 struct KObjectBaseInner {
     signal: Signal,
-    signal_callbacks: Vec<SignalHandler>,
+    /// The OR of every live registration's `interest`, so a `signal_change` touching no bit
+    /// anyone cares about can bail out without looking at a single registration.
+    interest: Signal,
+    /// A slab of registrations: `None` marks a freed slot, reused via `free_list`.
+    registrations: Vec<Option<Registration>>,
+    /// Per-slot generation, bumped every time a slot is (re)occupied. A [`RegistrationToken`]
+    /// carries the generation it was issued for, so a token that outlives its own callback's
+    /// self-retirement can't deregister an unrelated registration that has since reused the slot.
+    generations: Vec<u64>,
+    free_list: Vec<usize>,
+    /// The paired object (e.g. the other end of a channel), if any.
+    peer: Option<Weak<dyn KernelObject>>,
+}
+
+impl KObjectBaseInner {
+    fn insert(&mut self, interest: Signal, callback: SignalHandler) -> (usize, u64) {
+        self.interest.insert(interest);
+        let registration = Registration { interest, callback };
+        if let Some(key) = self.free_list.pop() {
+            self.registrations[key] = Some(registration);
+            self.generations[key] += 1;
+            (key, self.generations[key])
+        } else {
+            self.registrations.push(Some(registration));
+            self.generations.push(0);
+            (self.registrations.len() - 1, 0)
+        }
+    }
+
+    fn remove(&mut self, key: usize, generation: u64) {
+        // A stale token from a slot that was freed and reused belongs to a dead generation; treat
+        // it as already-removed rather than tearing down whatever now lives in that slot.
+        if self.generations.get(key) != Some(&generation) {
+            return;
+        }
+        if let Some(slot) = self.registrations.get_mut(key) {
+            if slot.take().is_some() {
+                self.free_list.push(key);
+                self.recompute_interest();
+            }
+        }
+    }
+
+    fn recompute_interest(&mut self) {
+        self.interest = self
+            .registrations
+            .iter()
+            .flatten()
+            .fold(Signal::empty(), |acc, r| acc | r.interest);
+    }
 }
 
 impl Default for KObjectBase {
@@ -57,10 +123,10 @@ impl KObjectBase {
     pub fn with_signal(signal: Signal) -> Self {
         KObjectBase {
             id: Self::new_koid(),
-            inner: Mutex::new(KObjectBaseInner {
+            inner: Arc::new(Mutex::new(KObjectBaseInner {
                 signal,
-                signal_callbacks: Vec::new(),
-            }),
+                ..Default::default()
+            })),
         }
     }
 
@@ -77,17 +143,38 @@ impl KObjectBase {
 
     /// Change signal status: first `clear` then `set` indicated bits.
     ///
-    /// All signal callbacks will be called.
+    /// Only callbacks whose `interest` overlaps the changed bits are invoked; if nothing changed
+    /// that anyone registered interest in, this returns without touching a single registration.
     pub fn signal_change(&self, clear: Signal, set: Signal) {
         let mut inner = self.inner.lock();
         let old_signal = inner.signal;
         inner.signal.remove(clear);
         inner.signal.insert(set);
         let new_signal = inner.signal;
-        if new_signal == old_signal {
+        let changed = old_signal ^ new_signal;
+        if changed.is_empty() || (inner.interest & changed).is_empty() {
             return;
         }
-        inner.signal_callbacks.retain(|f| !f(new_signal));
+        let mut retired = Vec::new();
+        for (key, slot) in inner.registrations.iter_mut().enumerate() {
+            // Gate on the bits that actually *changed*, not on which bits happen to be set in
+            // `new_signal` — a registration interested only in WRITABLE must not be woken by a
+            // READABLE transition just because WRITABLE was already (still) asserted.
+            let fire = match slot {
+                Some(registration) if !(registration.interest & changed).is_empty() => {
+                    (registration.callback)(new_signal)
+                }
+                _ => false,
+            };
+            if fire {
+                *slot = None;
+                retired.push(key);
+            }
+        }
+        if !retired.is_empty() {
+            inner.free_list.extend(retired);
+            inner.recompute_interest();
+        }
     }
 
     pub fn signal_set(&self, signal: Signal) {
@@ -98,14 +185,25 @@ impl KObjectBase {
         self.signal_change(signal, Signal::empty());
     }
 
-    /// Add `callback` for signal status changes.
+    /// Register `callback` to run whenever a signal change sets or clears a bit in `interest`.
+    /// It is passed the new signal value, which may no longer overlap `interest` after a clear.
     ///
-    /// The `callback` is a function of `Fn(Signal) -> bool`.
-    /// It returns a bool indicating whether the handle process is over.
-    /// If true, the function will never be called again.
-    pub fn add_signal_callback(&self, callback: SignalHandler) {
-        let mut inner = self.inner.lock();
-        inner.signal_callbacks.push(callback);
+    /// The `callback` is a function of `Fn(Signal) -> bool`. It returns a bool indicating
+    /// whether the handle process is over. If true, the function will never be called again.
+    ///
+    /// Returns a [`RegistrationToken`] whose `Drop` deregisters the callback; hold onto it for as
+    /// long as the wait is live, otherwise it is removed immediately.
+    pub fn add_signal_callback(
+        &self,
+        interest: Signal,
+        callback: SignalHandler,
+    ) -> RegistrationToken {
+        let (key, generation) = self.inner.lock().insert(interest, callback);
+        RegistrationToken {
+            inner: self.inner.clone(),
+            key,
+            generation,
+        }
     }
 
     /// Block until at least one `signal` assert. Return the current signal.
@@ -115,19 +213,308 @@ impl KObjectBase {
             return current_signal;
         }
         let waker = crate::hal::Thread::get_waker();
-        self.add_signal_callback(Box::new(move |s| {
-            if !(s & signal).is_empty() {
+        let _token = self.add_signal_callback(
+            signal,
+            // The dispatcher now calls this on any transition that touches `signal`, including a
+            // clear; only retire (and wake) once the bit is actually asserted.
+            Box::new(move |s| {
+                if (s & signal).is_empty() {
+                    return false;
+                }
                 waker.wake();
-                return true;
-            }
-            false
-        }));
+                true
+            }),
+        );
         while (current_signal & signal).is_empty() {
             crate::hal::Thread::park();
             current_signal = self.signal();
         }
         current_signal
     }
+
+    /// Block until at least one `signal` asserts or `deadline` passes, whichever comes first.
+    pub fn wait_signal_until(
+        &self,
+        signal: Signal,
+        deadline: Duration,
+    ) -> Result<Signal, TimedOut> {
+        let mut current_signal = self.signal();
+        if !(current_signal & signal).is_empty() {
+            return Ok(current_signal);
+        }
+        let waker = crate::hal::Thread::get_waker();
+        let _token = self.add_signal_callback(
+            signal,
+            Box::new({
+                let waker = waker.clone();
+                move |s| {
+                    if (s & signal).is_empty() {
+                        return false;
+                    }
+                    waker.wake();
+                    true
+                }
+            }),
+        );
+        crate::hal::timer_set(deadline, Box::new(move || waker.wake()));
+        loop {
+            // Check the signal before the deadline: if both are ready, Zircon's wait-one prefers
+            // the signal, so a signal already asserted when this re-check runs wins even if the
+            // deadline has also passed. A signal asserted after that re-check gets `TimedOut`.
+            current_signal = self.signal();
+            if !(current_signal & signal).is_empty() {
+                return Ok(current_signal);
+            }
+            if crate::hal::timer_now() >= deadline {
+                return Err(TimedOut);
+            }
+            crate::hal::Thread::park();
+        }
+    }
+
+    /// Register a port wait: whenever `signal` asserts, queue a [`PortPacket`] tagged with `key`
+    /// into `port`. If `once` is true (edge-triggered) the registration retires after the first
+    /// delivery; otherwise (level-triggered) it stays armed and fires again on every future
+    /// assertion.
+    pub fn wait_async(
+        &self,
+        port: &Arc<Port>,
+        key: u64,
+        signal: Signal,
+        once: bool,
+    ) -> AsyncWaitHandle {
+        let port = port.clone();
+        let token = self.add_signal_callback(
+            signal,
+            Box::new(move |s| {
+                // The dispatcher fires on any transition touching `signal`, including a clear;
+                // only queue a packet (and possibly retire) once the bit is actually asserted.
+                if (s & signal).is_empty() {
+                    return false;
+                }
+                port.queue_packet(PortPacket {
+                    key,
+                    observed_signal: s & signal,
+                    trigger_signal: signal,
+                });
+                once
+            }),
+        );
+        AsyncWaitHandle { _token: token }
+    }
+
+    /// Link this object to its peer (e.g. the other end of a channel), so [`signal_peer`] can
+    /// reach it and `related_koid` can report it.
+    ///
+    /// [`signal_peer`]: KObjectBase::signal_peer
+    pub fn set_peer(&self, peer: Weak<dyn KernelObject>) {
+        self.inner.lock().peer = Some(peer);
+    }
+
+    /// The linked peer, if it is still alive.
+    pub fn peer(&self) -> Option<Arc<dyn KernelObject>> {
+        self.inner.lock().peer.as_ref()?.upgrade()
+    }
+
+    /// The `KoID` of the linked peer, or 0 if none (or it has already been dropped).
+    pub fn related_koid(&self) -> KoID {
+        self.peer().map_or(0, |peer| peer.id())
+    }
+
+    /// Apply `signal_change` to the linked peer, if it is still alive.
+    ///
+    /// This is how one end of a pair (e.g. a channel) wakes everyone waiting on the other end,
+    /// most importantly to assert `PEER_CLOSED`.
+    pub fn signal_peer(&self, clear: Signal, set: Signal) {
+        if let Some(peer) = self.peer() {
+            peer.signal_change(clear, set);
+        }
+    }
+}
+
+impl Drop for KObjectBase {
+    /// Assert `PEER_CLOSED` on the peer once the last strong reference to this end is dropped.
+    fn drop(&mut self) {
+        self.signal_peer(Signal::empty(), Signal::PEER_CLOSED);
+    }
+}
+
+/// A handle to a registered signal callback, returned by [`KObjectBase::add_signal_callback`].
+///
+/// Dropping it deregisters the callback. This is what lets every wait primitive in this module
+/// clean up after itself instead of leaking dead entries into the target object forever.
+pub struct RegistrationToken {
+    inner: Arc<Mutex<KObjectBaseInner>>,
+    key: usize,
+    /// The slot's generation at registration time; guards against deregistering a different
+    /// registration that has since reused the slot.
+    generation: u64,
+}
+
+impl Drop for RegistrationToken {
+    fn drop(&mut self) {
+        self.inner.lock().remove(self.key, self.generation);
+    }
+}
+
+/// A handle to an in-flight [`KObjectBase::wait_async`] registration.
+///
+/// Dropping or [`cancel`](AsyncWaitHandle::cancel)-ing it deregisters the underlying callback, so
+/// no more packets are queued for it.
+pub struct AsyncWaitHandle {
+    _token: RegistrationToken,
+}
+
+impl AsyncWaitHandle {
+    /// Cancel the wait: the target object will stop delivering packets for it.
+    pub fn cancel(self) {}
+}
+
+/// The result of a [`wait_signal_many`] or [`wait_signal_many_async`] call.
+#[derive(Debug, Clone)]
+pub struct WaitManyResult {
+    /// The signal of each object, sampled at the moment the wait completed.
+    pub signals: Vec<Signal>,
+    /// The index of the item (into the slice passed to the wait) found satisfying it. If several
+    /// items are asserted when the wait completes, this is not necessarily the one that asserted
+    /// first.
+    pub index: usize,
+}
+
+/// Block until one of `items` asserts its wanted `Signal`.
+///
+/// This is the synchronous analog of [`wait_signal_many_async`], akin to `zx_object_wait_many`.
+pub fn wait_signal_many(items: &[(Arc<dyn KernelObject>, Signal)]) -> WaitManyResult {
+    if let Some(index) = items
+        .iter()
+        .position(|(o, s)| !(o.signal() & *s).is_empty())
+    {
+        return WaitManyResult {
+            signals: items.iter().map(|(o, _)| o.signal()).collect(),
+            index,
+        };
+    }
+    let waker = crate::hal::Thread::get_waker();
+    let satisfied: Arc<Mutex<Option<usize>>> = Arc::new(Mutex::new(None));
+    let _tokens: Vec<RegistrationToken> = items
+        .iter()
+        .enumerate()
+        .map(|(i, (object, signal))| {
+            let waker = waker.clone();
+            let satisfied = satisfied.clone();
+            let signal = *signal;
+            object.add_signal_callback(
+                signal,
+                Box::new(move |s| {
+                    if (s & signal).is_empty() {
+                        return false;
+                    }
+                    satisfied.lock().get_or_insert(i);
+                    waker.wake();
+                    true
+                }),
+            )
+        })
+        .collect();
+    // A signal can assert between the initial scan and a given item's callback being registered;
+    // that item's callback never sees the transition and so never wakes us. Re-scan once now that
+    // every item is registered, so such a signal is still caught instead of hanging forever.
+    if let Some(index) = items
+        .iter()
+        .position(|(o, s)| !(o.signal() & *s).is_empty())
+    {
+        return WaitManyResult {
+            signals: items.iter().map(|(o, _)| o.signal()).collect(),
+            index,
+        };
+    }
+    loop {
+        if let Some(index) = *satisfied.lock() {
+            return WaitManyResult {
+                signals: items.iter().map(|(o, _)| o.signal()).collect(),
+                index,
+            };
+        }
+        crate::hal::Thread::park();
+    }
+}
+
+/// Asynchronously wait until one of `items` asserts its wanted `Signal`.
+///
+/// Akin to `zx_object_wait_many`: on completion, returns the signal sampled from every item
+/// plus the index of the one that satisfied the wait.
+pub fn wait_signal_many_async(
+    items: Vec<(Arc<dyn KernelObject>, Signal)>,
+) -> impl Future<Output = WaitManyResult> {
+    struct WaitManyFuture {
+        items: Vec<(Arc<dyn KernelObject>, Signal)>,
+        satisfied: Arc<Mutex<Option<usize>>>,
+        tokens: Vec<RegistrationToken>,
+    }
+
+    impl Future for WaitManyFuture {
+        type Output = WaitManyResult;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            for (i, (object, signal)) in self.items.iter().enumerate() {
+                if !(object.signal() & *signal).is_empty() {
+                    return Poll::Ready(WaitManyResult {
+                        signals: self.items.iter().map(|(o, _)| o.signal()).collect(),
+                        index: i,
+                    });
+                }
+            }
+            if self.tokens.is_empty() {
+                let satisfied = self.satisfied.clone();
+                self.tokens = self
+                    .items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (object, signal))| {
+                        let waker = cx.waker().clone();
+                        let satisfied = satisfied.clone();
+                        let signal = *signal;
+                        object.add_signal_callback(
+                            signal,
+                            Box::new(move |s| {
+                                if (s & signal).is_empty() {
+                                    return false;
+                                }
+                                satisfied.lock().get_or_insert(i);
+                                waker.wake_by_ref();
+                                true
+                            }),
+                        )
+                    })
+                    .collect();
+                // A signal can assert between the scan above and a given item's callback being
+                // registered; that item's callback never sees the transition and so never wakes
+                // us. Re-scan once now that every item is registered, so such a signal is still
+                // caught instead of leaving this future pending forever.
+                for (i, (object, signal)) in self.items.iter().enumerate() {
+                    if !(object.signal() & *signal).is_empty() {
+                        return Poll::Ready(WaitManyResult {
+                            signals: self.items.iter().map(|(o, _)| o.signal()).collect(),
+                            index: i,
+                        });
+                    }
+                }
+            }
+            if let Some(index) = *self.satisfied.lock() {
+                return Poll::Ready(WaitManyResult {
+                    signals: self.items.iter().map(|(o, _)| o.signal()).collect(),
+                    index,
+                });
+            }
+            Poll::Pending
+        }
+    }
+
+    WaitManyFuture {
+        items,
+        satisfied: Arc::new(Mutex::new(None)),
+        tokens: Vec::new(),
+    }
 }
 
 impl dyn KernelObject {
@@ -136,7 +523,7 @@ impl dyn KernelObject {
         struct SignalFuture {
             object: Arc<dyn KernelObject>,
             signal: Signal,
-            first: bool,
+            token: Option<RegistrationToken>,
         }
 
         impl Future for SignalFuture {
@@ -147,19 +534,19 @@ impl dyn KernelObject {
                 if !(current_signal & self.signal).is_empty() {
                     return Poll::Ready(current_signal);
                 }
-                if self.first {
-                    self.object.add_signal_callback(Box::new({
-                        let signal = self.signal;
-                        let waker = cx.waker().clone();
-                        move |s| {
-                            if !(s & signal).is_empty() {
-                                waker.wake_by_ref();
-                                return true;
+                if self.token.is_none() {
+                    let waker = cx.waker().clone();
+                    let signal = self.signal;
+                    self.token = Some(self.object.add_signal_callback(
+                        signal,
+                        Box::new(move |s| {
+                            if (s & signal).is_empty() {
+                                return false;
                             }
-                            false
-                        }
-                    }));
-                    self.first = false;
+                            waker.wake_by_ref();
+                            true
+                        }),
+                    ));
                 }
                 Poll::Pending
             }
@@ -168,7 +555,129 @@ impl dyn KernelObject {
         SignalFuture {
             object: self.clone(),
             signal,
-            first: true,
+            token: None,
+        }
+    }
+
+    /// Asynchronous wait for one of `signal`, racing it against `deadline`.
+    ///
+    /// Resolves to `Err(TimedOut)` once the HAL clock passes `deadline` before the signal does.
+    pub fn wait_signal_async_until(
+        self: &Arc<Self>,
+        signal: Signal,
+        deadline: Duration,
+    ) -> impl Future<Output = Result<Signal, TimedOut>> {
+        struct SignalUntilFuture {
+            object: Arc<dyn KernelObject>,
+            signal: Signal,
+            deadline: Duration,
+            token: Option<RegistrationToken>,
+        }
+
+        impl Future for SignalUntilFuture {
+            type Output = Result<Signal, TimedOut>;
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                let current_signal = self.object.signal();
+                if !(current_signal & self.signal).is_empty() {
+                    return Poll::Ready(Ok(current_signal));
+                }
+                if crate::hal::timer_now() >= self.deadline {
+                    return Poll::Ready(Err(TimedOut));
+                }
+                if self.token.is_none() {
+                    let waker = cx.waker().clone();
+                    let signal = self.signal;
+                    self.token = Some(self.object.add_signal_callback(
+                        signal,
+                        Box::new(move |s| {
+                            if (s & signal).is_empty() {
+                                return false;
+                            }
+                            waker.wake_by_ref();
+                            true
+                        }),
+                    ));
+                    crate::hal::timer_set(self.deadline, {
+                        let waker = cx.waker().clone();
+                        Box::new(move || waker.wake_by_ref())
+                    });
+                }
+                Poll::Pending
+            }
+        }
+
+        SignalUntilFuture {
+            object: self.clone(),
+            signal,
+            deadline,
+            token: None,
+        }
+    }
+
+    /// Stream every signal transition that touches `interest`, instead of resolving once like
+    /// [`wait_signal_async`](Self::wait_signal_async).
+    ///
+    /// Useful for an object (e.g. a channel or socket) that oscillates between signals over
+    /// time: each transition is pushed into a small ring buffer and handed out in order, so the
+    /// caller never has to re-register after consuming one. The buffer holds at most 16
+    /// transitions; once it is full, each new transition drops the oldest unconsumed one.
+    /// Dropping the stream deregisters the underlying callback.
+    pub fn signal_stream(self: &Arc<Self>, interest: Signal) -> impl Stream<Item = Signal> {
+        #[derive(Default)]
+        struct SignalStreamState {
+            buffer: VecDeque<Signal>,
+            waker: Option<Waker>,
+        }
+
+        struct SignalStream {
+            // `buffer` and `waker` must live behind a single lock: deciding "nothing buffered,
+            // so park the waker" has to be atomic with the callback's "push, then wake", or a
+            // push landing between the two halves of `poll_next` is a lost wakeup.
+            state: Arc<Mutex<SignalStreamState>>,
+            _token: RegistrationToken,
+        }
+
+        impl Stream for SignalStream {
+            type Item = Signal;
+
+            fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+                let mut state = self.state.lock();
+                if let Some(signal) = state.buffer.pop_front() {
+                    return Poll::Ready(Some(signal));
+                }
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+
+        // Cap the ring buffer: a producer that outpaces the consumer (e.g. a stream nobody is
+        // polling) must not grow memory without bound. Each transition is still a point-in-time
+        // sample, so once full we drop the oldest rather than block the signaling side.
+        const MAX_BUFFERED: usize = 16;
+
+        let state = Arc::new(Mutex::new(SignalStreamState::default()));
+        let token = self.add_signal_callback(
+            interest,
+            Box::new({
+                let state = state.clone();
+                move |s| {
+                    let mut state = state.lock();
+                    if state.buffer.len() >= MAX_BUFFERED {
+                        state.buffer.pop_front();
+                    }
+                    state.buffer.push_back(s);
+                    if let Some(w) = state.waker.take() {
+                        w.wake();
+                    }
+                    // Returning `false` always keeps this callback armed, unlike one-shot waits.
+                    false
+                }
+            }),
+        );
+        SignalStream {
+            state,
+            _token: token,
         }
     }
 }
@@ -186,8 +695,18 @@ macro_rules! impl_kobject {
             fn signal(&self) -> Signal {
                 self.base.signal()
             }
-            fn add_signal_callback(&self, callback: SignalHandler) {
-                self.base.add_signal_callback(callback);
+            fn add_signal_callback(
+                &self,
+                interest: Signal,
+                callback: SignalHandler,
+            ) -> RegistrationToken {
+                self.base.add_signal_callback(interest, callback)
+            }
+            fn signal_change(&self, clear: Signal, set: Signal) {
+                self.base.signal_change(clear, set)
+            }
+            fn related_koid(&self) -> KoID {
+                self.base.related_koid()
             }
         }
         impl core::fmt::Debug for $class {
@@ -205,6 +724,10 @@ pub type KoID = u64;
 
 pub type SignalHandler = Box<dyn Fn(Signal) -> bool + Send>;
 
+/// Returned by the deadline-bounded wait variants once the deadline passes before the signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -275,4 +798,299 @@ mod tests {
         assert_eq!(signal, Signal::READABLE);
         assert_eq!(flag.load(Ordering::SeqCst), 1);
     }
+
+    #[test]
+    fn wait_many() {
+        let object1 = DummyObject::new();
+        let object2 = DummyObject::new();
+        std::thread::spawn({
+            let object2 = object2.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(1));
+                object2.base.signal_set(Signal::WRITABLE);
+            }
+        });
+        let items: Vec<(Arc<dyn KernelObject>, Signal)> =
+            vec![(object1, Signal::READABLE), (object2, Signal::WRITABLE)];
+        let result = wait_signal_many(&items);
+        assert_eq!(result.index, 1);
+        assert_eq!(result.signals[1], Signal::WRITABLE);
+    }
+
+    #[test]
+    fn wait_many_async() {
+        let object1 = DummyObject::new();
+        let object2 = DummyObject::new();
+
+        let mut pool = futures::executor::LocalPool::new();
+        pool.spawner()
+            .spawn({
+                let object2 = object2.clone();
+                async move {
+                    object2.base.signal_set(Signal::WRITABLE);
+                }
+            })
+            .unwrap();
+        let items: Vec<(Arc<dyn KernelObject>, Signal)> =
+            vec![(object1, Signal::READABLE), (object2, Signal::WRITABLE)];
+        let result = pool.run_until(wait_signal_many_async(items));
+        assert_eq!(result.index, 1);
+        assert_eq!(result.signals[1], Signal::WRITABLE);
+    }
+
+    #[test]
+    fn registration_is_removed_on_drop() {
+        let object = DummyObject::new();
+        {
+            let _token = object
+                .base
+                .add_signal_callback(Signal::READABLE, Box::new(|_| false));
+            assert_eq!(object.base.inner.lock().registrations.len(), 1);
+        }
+        assert_eq!(object.base.inner.lock().free_list.len(), 1);
+        assert_eq!(object.base.inner.lock().interest, Signal::empty());
+    }
+
+    #[test]
+    fn stale_token_does_not_evict_reused_slot() {
+        let object = DummyObject::new();
+
+        // A one-shot callback that self-retires (returns `true`) without its token ever being
+        // dropped, so the slot is freed while a live `RegistrationToken` still points at it.
+        let stale_token = object
+            .base
+            .add_signal_callback(Signal::READABLE, Box::new(|_| true));
+        object.base.signal_set(Signal::READABLE);
+        assert_eq!(object.base.inner.lock().free_list.len(), 1);
+
+        // A second registration reuses that freed slot.
+        let _live_token = object
+            .base
+            .add_signal_callback(Signal::WRITABLE, Box::new(|_| false));
+        assert_eq!(
+            object
+                .base
+                .inner
+                .lock()
+                .registrations
+                .iter()
+                .flatten()
+                .count(),
+            1
+        );
+
+        // Dropping the stale token must not evict the registration that now occupies its old slot.
+        drop(stale_token);
+        assert_eq!(
+            object
+                .base
+                .inner
+                .lock()
+                .registrations
+                .iter()
+                .flatten()
+                .count(),
+            1
+        );
+        assert_eq!(object.base.inner.lock().interest, Signal::WRITABLE);
+    }
+
+    #[test]
+    fn port_queue_user_packet() {
+        let port = Port::new();
+        port.queue_user_packet(PortPacket {
+            key: 5,
+            observed_signal: Signal::READABLE,
+            trigger_signal: Signal::READABLE,
+        });
+        let packet = port.wait();
+        assert_eq!(packet.key, 5);
+    }
+
+    #[test]
+    fn port_wait_async_once_retires_after_first_delivery() {
+        let object = DummyObject::new();
+        let port = Port::new();
+
+        let _handle = object.base.wait_async(&port, 42, Signal::WRITABLE, true);
+        object.base.signal_set(Signal::WRITABLE);
+
+        let packet = port.wait();
+        assert_eq!(packet.key, 42);
+        assert_eq!(packet.trigger_signal, Signal::WRITABLE);
+        assert_eq!(packet.observed_signal, Signal::WRITABLE);
+
+        // Edge-triggered (`once = true`): the registration self-retires after that single
+        // delivery, so a further assertion queues nothing more.
+        assert_eq!(
+            object
+                .base
+                .inner
+                .lock()
+                .registrations
+                .iter()
+                .flatten()
+                .count(),
+            0
+        );
+        object.base.signal_clear(Signal::WRITABLE);
+        object.base.signal_set(Signal::WRITABLE);
+        assert_eq!(object.base.inner.lock().interest, Signal::empty());
+    }
+
+    #[test]
+    fn port_wait_async_level_rearms() {
+        let object = DummyObject::new();
+        let port = Port::new();
+
+        let _handle = object.base.wait_async(&port, 7, Signal::READABLE, false);
+        object.base.signal_set(Signal::READABLE);
+        object.base.signal_clear(Signal::READABLE);
+        object.base.signal_set(Signal::READABLE);
+
+        let first = port.wait();
+        let second = port.wait();
+        assert_eq!(first.key, 7);
+        assert_eq!(second.key, 7);
+    }
+
+    #[test]
+    fn port_readable_matches_queue_under_concurrent_producer() {
+        let port = Port::new();
+        let count = 200;
+        let producer = {
+            let port = port.clone();
+            std::thread::spawn(move || {
+                for i in 0..count {
+                    port.queue_user_packet(PortPacket {
+                        key: i as u64,
+                        observed_signal: Signal::READABLE,
+                        trigger_signal: Signal::READABLE,
+                    });
+                }
+            })
+        };
+        for _ in 0..count {
+            port.wait();
+        }
+        producer.join().unwrap();
+
+        // The queue is now fully drained; `READABLE` must not be stuck asserted, which would
+        // otherwise busy-spin every future `Port::wait`/`Port::wait_async` caller forever.
+        assert_eq!(port.signal() & Signal::READABLE, Signal::empty());
+    }
+
+    #[test]
+    fn port_wait_async_cancel_stops_delivery() {
+        let object = DummyObject::new();
+        let port = Port::new();
+
+        let handle = object.base.wait_async(&port, 9, Signal::READABLE, false);
+        handle.cancel();
+        object.base.signal_set(Signal::READABLE);
+
+        assert_eq!(
+            object
+                .base
+                .inner
+                .lock()
+                .registrations
+                .iter()
+                .flatten()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn wait_signal_until_times_out() {
+        let object = DummyObject::new();
+        let deadline = crate::hal::timer_now() + Duration::from_millis(1);
+        let result = object.base.wait_signal_until(Signal::READABLE, deadline);
+        assert_eq!(result, Err(TimedOut));
+    }
+
+    #[test]
+    fn wait_signal_until_prefers_signal_at_deadline() {
+        let object = DummyObject::new();
+        // The signal lands right as the deadline elapses. Zircon's wait-one prefers an
+        // already-satisfied signal over a timeout at the same instant, so this must still resolve
+        // `Ok` rather than `Err(TimedOut)`.
+        let deadline = crate::hal::timer_now() + Duration::from_millis(2);
+        std::thread::spawn({
+            let object = object.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(2));
+                object.base.signal_set(Signal::READABLE);
+            }
+        });
+        let result = object.base.wait_signal_until(Signal::READABLE, deadline);
+        assert_eq!(result, Ok(Signal::READABLE));
+    }
+
+    #[test]
+    fn signal_stream_yields_each_transition() {
+        use futures::StreamExt;
+
+        let concrete = DummyObject::new();
+        let object: Arc<dyn KernelObject> = concrete.clone();
+        let mut stream = Box::pin(object.signal_stream(Signal::READABLE | Signal::WRITABLE));
+
+        let mut pool = futures::executor::LocalPool::new();
+        pool.spawner()
+            .spawn({
+                let concrete = concrete.clone();
+                async move {
+                    concrete.base.signal_set(Signal::READABLE);
+                    concrete.base.signal_set(Signal::WRITABLE);
+                }
+            })
+            .unwrap();
+
+        let first = pool.run_until(stream.next()).unwrap();
+        assert_eq!(first, Signal::READABLE);
+        let second = pool.run_until(stream.next()).unwrap();
+        assert_eq!(second, Signal::READABLE | Signal::WRITABLE);
+    }
+
+    #[test]
+    fn signal_stream_bounds_buffered_transitions() {
+        let concrete = DummyObject::new();
+        let object: Arc<dyn KernelObject> = concrete.clone();
+        let mut stream = Box::pin(object.signal_stream(Signal::READABLE));
+
+        // Flood far more transitions than the ring buffer can hold without ever draining it, as
+        // happens when nobody is polling the stream.
+        for _ in 0..1000 {
+            concrete.base.signal_set(Signal::READABLE);
+            concrete.base.signal_clear(Signal::READABLE);
+        }
+
+        let waker = futures::task::noop_waker_ref();
+        let mut cx = Context::from_waker(waker);
+        let mut drained = 0;
+        while let Poll::Ready(Some(_)) = stream.as_mut().poll_next(&mut cx) {
+            drained += 1;
+        }
+        assert!(drained <= 16, "buffer should be capped, got {drained}");
+    }
+
+    #[test]
+    fn peer_closed_on_drop() {
+        let a = DummyObject::new();
+        let b = DummyObject::new();
+        let a_dyn: Arc<dyn KernelObject> = a.clone();
+        let b_dyn: Arc<dyn KernelObject> = b.clone();
+        a.base.set_peer(Arc::downgrade(&b_dyn));
+        b.base.set_peer(Arc::downgrade(&a_dyn));
+
+        assert_eq!(a.base.related_koid(), b.id());
+        assert_eq!(b.base.related_koid(), a.id());
+
+        drop(a);
+        drop(a_dyn);
+
+        assert!(!(b.base.signal() & Signal::PEER_CLOSED).is_empty());
+        assert_eq!(b.base.related_koid(), 0);
+    }
 }
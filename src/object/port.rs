@@ -0,0 +1,97 @@
+use {
+    super::*,
+    alloc::{collections::VecDeque, sync::Arc},
+    spin::Mutex,
+};
+
+/// A packet delivered through a [`Port`], describing a single signal observation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortPacket {
+    /// The key the observer registered with [`KObjectBase::wait_async`].
+    pub key: u64,
+    /// The signal bits that were actually observed when the packet was queued.
+    pub observed_signal: Signal,
+    /// The signal mask the observer was waiting for.
+    pub trigger_signal: Signal,
+}
+
+/// A Zircon-style Port: a FIFO queue of [`PortPacket`]s, fed by `wait_async` registrations on
+/// other kernel objects and drained by whoever owns the port.
+///
+/// This is the single place readiness from many objects multiplexes through, which is what lets
+/// a syscall layer built on top of this crate scale to watching thousands of handles.
+pub struct Port {
+    base: KObjectBase,
+    inner: Mutex<PortInner>,
+}
+
+#[derive(Default)]
+struct PortInner {
+    queue: VecDeque<PortPacket>,
+}
+
+impl_kobject!(Port);
+
+impl Port {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Port {
+            base: KObjectBase::new(),
+            inner: Mutex::new(PortInner::default()),
+        })
+    }
+
+    /// Push a packet and wake anyone parked in [`Port::wait`] or polling [`Port::wait_async`].
+    pub(super) fn queue_packet(&self, packet: PortPacket) {
+        let mut inner = self.inner.lock();
+        inner.queue.push_back(packet);
+        // Assert `READABLE` while still holding `inner`, so this can't interleave with
+        // `pop_packet`'s own queue-emptiness check: either this push lands before `pop_packet`
+        // looks (and it sees the new packet) or after (and it leaves `READABLE` set for us) —
+        // never in the gap where `pop_packet` would otherwise clear `READABLE` out from under a
+        // packet that arrives right after the queue briefly drained.
+        self.base.signal_set(Signal::READABLE);
+    }
+
+    /// Let user code inject a packet directly, bypassing the `wait_async` signal machinery.
+    pub fn queue_user_packet(&self, packet: PortPacket) {
+        self.queue_packet(packet);
+    }
+
+    /// Pop the next packet, if any, clearing `READABLE` once the queue drains.
+    fn pop_packet(&self) -> Option<PortPacket> {
+        let mut inner = self.inner.lock();
+        let packet = inner.queue.pop_front();
+        // Decide and apply the `READABLE` clear while still holding `inner`, so a concurrent
+        // `queue_packet` either lands before this (and we see its packet) or blocks until after
+        // (and re-asserts `READABLE` itself) — never in between, where we'd clear it out from
+        // under a packet that just arrived.
+        if packet.is_some() && inner.queue.is_empty() {
+            self.base.signal_clear(Signal::READABLE);
+        }
+        packet
+    }
+
+    /// Block until a packet is available and return it, FIFO order.
+    pub fn wait(&self) -> PortPacket {
+        loop {
+            if let Some(packet) = self.pop_packet() {
+                return packet;
+            }
+            self.base.wait_signal(Signal::READABLE);
+        }
+    }
+
+    /// Asynchronously wait for a packet to become available.
+    pub fn wait_async(self: &Arc<Self>) -> impl core::future::Future<Output = PortPacket> {
+        let port = self.clone();
+        async move {
+            loop {
+                if let Some(packet) = port.pop_packet() {
+                    return packet;
+                }
+                let object: Arc<dyn KernelObject> = port.clone();
+                object.wait_signal_async(Signal::READABLE).await;
+            }
+        }
+    }
+}